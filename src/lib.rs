@@ -12,6 +12,9 @@ use bindings::*;
 
 use std::ffi::{CStr, CString};
 use std::fmt;
+use std::io::Write;
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::{Arc, Mutex, OnceLock};
 
 /// Represents an error that occurred during Go template rendering.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,8 +28,145 @@ impl fmt::Display for TemplateError {
 
 impl std::error::Error for TemplateError {}
 
+/// A Rust closure registered via [`register_func`] as a Go template function.
+///
+/// Stored behind an `Arc` (rather than a bare `Box`) so [`invoke_func`] can
+/// clone the closure out of the registry and release the registry lock
+/// before calling it; the call may otherwise re-enter [`register_func`] or
+/// take arbitrarily long, and holding the lock across it would risk
+/// deadlock or, on panic, poisoning every future lookup.
+type RegisteredFunc =
+    Arc<dyn Fn(&[serde_json::Value]) -> Result<serde_json::Value, String> + Send + Sync>;
+
+fn func_registry() -> &'static Mutex<Vec<(String, RegisteredFunc)>> {
+    static REGISTRY: OnceLock<Mutex<Vec<(String, RegisteredFunc)>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Registers `f` as a Go template function, callable as `{{name .Arg}}` from
+/// every [`Template`]/[`TemplateSet`] parsed afterwards (and from
+/// [`render_template`]).
+///
+/// There is currently no way to unregister a function; the registry lives
+/// for the lifetime of the process.
+pub fn register_func<F>(name: impl Into<String>, f: F)
+where
+    F: Fn(&[serde_json::Value]) -> Result<serde_json::Value, String> + Send + Sync + 'static,
+{
+    func_registry()
+        .lock()
+        .unwrap()
+        .push((name.into(), Arc::new(f)));
+}
+
+#[derive(serde::Serialize)]
+struct FuncEntry<'a> {
+    name: &'a str,
+    id: u64,
+}
+
+/// Builds the JSON `[{"name": ..., "id": ...}, ...]` list that Go turns into
+/// a `template.FuncMap`, one entry per function registered with
+/// [`register_func`].
+fn registered_funcs_json() -> Result<CString, TemplateError> {
+    let registry = func_registry().lock().unwrap();
+    let entries: Vec<FuncEntry> = registry
+        .iter()
+        .enumerate()
+        .map(|(id, (name, _))| FuncEntry {
+            name,
+            id: id as u64,
+        })
+        .collect();
+    let json = serde_json::to_string(&entries)
+        .map_err(|e| TemplateError(format!("Failed to serialize registered functions: {}", e)))?;
+    CString::new(json).map_err(|e| {
+        TemplateError(format!(
+            "Failed to convert registered functions to CString: {}",
+            e
+        ))
+    })
+}
+
+/// C trampoline that Go calls to invoke a registered Rust template function.
+///
+/// Decodes `args_json` (a JSON array), dispatches to the closure registered
+/// under `id`, and returns a JSON-encoded `{"ok": value}` /
+/// `{"is_err": true, "err": message}` envelope. `is_err` discriminates the
+/// branch explicitly rather than a non-empty `err`, so `Err(String::new())`
+/// still round-trips as an error. The returned string is owned by Rust
+/// (`CString::into_raw`); Go must free it via [`free_invoke_result`] rather
+/// than `free()`.
+extern "C" fn invoke_func(id: u64, args_json: *const c_char) -> *mut c_char {
+    let outcome: Result<serde_json::Value, String> = (|| {
+        let args_str = unsafe { CStr::from_ptr(args_json) }.to_string_lossy();
+        let args: Vec<serde_json::Value> =
+            serde_json::from_str(&args_str).map_err(|e| e.to_string())?;
+        // Clone the `Arc`'d closure out and drop the registry lock before
+        // calling it, so the call can't deadlock on re-entrant registration
+        // and can't poison the registry for every other function if it panics.
+        let f = {
+            let registry = func_registry().lock().unwrap();
+            registry
+                .get(id as usize)
+                .map(|(_, f)| Arc::clone(f))
+                .ok_or_else(|| format!("unknown template function id: {}", id))?
+        };
+        // A panic here (e.g. an `unwrap()` on a missing arg) must not unwind
+        // across the cgo boundary, which would abort the whole process
+        // instead of surfacing as a template execution error. Catch it and
+        // fold it into the same error envelope as a returned `Err`.
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&args))).unwrap_or_else(|p| {
+            let message = p
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| p.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "template function panicked".to_string());
+            Err(format!("template function panicked: {}", message))
+        })
+    })();
+
+    // `is_err` discriminates the envelope explicitly rather than relying on
+    // `err` being non-empty, so a closure that returns `Err(String::new())`
+    // still surfaces as a template execution error on the Go side.
+    let envelope = match outcome {
+        Ok(value) => serde_json::json!({ "ok": value }),
+        Err(message) => serde_json::json!({ "is_err": true, "err": message }),
+    };
+    let encoded = serde_json::to_string(&envelope).unwrap_or_else(|e| {
+        format!(
+            r#"{{"is_err":true,"err":"failed to encode function result: {}"}}"#,
+            e
+        )
+    });
+    CString::new(encoded)
+        .unwrap_or_else(|_| {
+            CString::new(r#"{"is_err":true,"err":"function result contained a NUL byte"}"#)
+                .unwrap()
+        })
+        .into_raw()
+}
+
+/// C trampoline Go calls to free a result string previously returned by
+/// [`invoke_func`].
+///
+/// The string was allocated on the Rust side via `CString::into_raw`, so it
+/// must be reclaimed with `CString::from_raw` rather than libc's `free` —
+/// freeing Rust-allocated memory with the C allocator is only safe by
+/// coincidence (Rust's default allocator happens to be malloc-backed) and
+/// would break under a custom `#[global_allocator]`.
+extern "C" fn free_invoke_result(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        unsafe { drop(CString::from_raw(ptr)) };
+    }
+}
+
 /// Renders a Go template with provided data.
 ///
+/// This re-parses `template_content` on every call. For hot paths that render
+/// the same template repeatedly (e.g. once per request), parse it once with
+/// [`Template::new`] and call [`Template::render`] instead.
+///
 /// # Arguments
 /// * `template_content` - The Go template string.
 /// * `data` - The data to be used in the template. This can be any type that implements `serde::Serialize`,
@@ -40,6 +180,46 @@ pub fn render_template<T: serde::Serialize>(
     template_content: &str,
     data: &T, // 接受任何 Serialize 类型
     escape_html: bool,
+) -> Result<String, TemplateError> {
+    render_template_with(
+        template_content,
+        data,
+        &RenderOptions {
+            escape_html,
+            ..Default::default()
+        },
+    )
+}
+
+/// Options controlling how [`render_template_with`] parses a template,
+/// beyond the simple `escape_html` flag accepted by [`render_template`].
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Whether to escape HTML characters in the output (`html/template`)
+    /// or not (`text/template`).
+    pub escape_html: bool,
+    /// Left action delimiter, e.g. `<%`. Defaults to Go's `{{` when `None`.
+    pub left_delim: Option<String>,
+    /// Right action delimiter, e.g. `%>`. Defaults to Go's `}}` when `None`.
+    pub right_delim: Option<String>,
+}
+
+/// Renders a Go template with provided data, using `options` to control
+/// escaping and (optionally) rebind the `{{`/`}}` action delimiters —
+/// useful when the template content collides with `{{ }}` used by another
+/// templating system or framework.
+///
+/// # Arguments
+/// * `template_content` - The Go template string.
+/// * `data` - The data to be used in the template, serialized to JSON as in [`render_template`].
+/// * `options` - Parsing options; see [`RenderOptions`].
+///
+/// # Returns
+/// A `Result` indicating success (`String` with rendered output) or failure (`TemplateError`).
+pub fn render_template_with<T: serde::Serialize>(
+    template_content: &str,
+    data: &T,
+    options: &RenderOptions,
 ) -> Result<String, TemplateError> {
     // 将 Rust 字符串转换为 C 字符串，以便传递给 Go
     let c_template_content = CString::new(template_content).map_err(|e| {
@@ -61,12 +241,24 @@ pub fn render_template<T: serde::Serialize>(
         ))
     })?;
 
+    let c_left_delim = CString::new(options.left_delim.clone().unwrap_or_default())
+        .map_err(|e| TemplateError(format!("Failed to convert left_delim to CString: {}", e)))?;
+    let c_right_delim = CString::new(options.right_delim.clone().unwrap_or_default())
+        .map_err(|e| TemplateError(format!("Failed to convert right_delim to CString: {}", e)))?;
+
+    let c_funcs_json = registered_funcs_json()?;
+
     // 调用 Go 函数。这是不安全的，因为涉及 FFI。
     let result = unsafe {
         RenderTemplate(
             c_template_content.as_ptr() as *mut i8,
             c_json_data.as_ptr() as *mut i8,
-            escape_html, // 传递 escape_html 参数
+            options.escape_html,
+            c_left_delim.as_ptr() as *mut i8,
+            c_right_delim.as_ptr() as *mut i8,
+            c_funcs_json.as_ptr() as *mut i8,
+            Some(invoke_func),
+            Some(free_invoke_result),
         )
     };
 
@@ -88,6 +280,436 @@ pub fn render_template<T: serde::Serialize>(
     }
 }
 
+/// Context passed across the FFI boundary to [`write_callback`]: the
+/// destination writer, plus anywhere to stash an I/O error so it can be
+/// reported after Go aborts execution.
+struct WriterCtx<'a, W: Write> {
+    writer: &'a mut W,
+    error: Option<std::io::Error>,
+}
+
+/// C callback Go calls with each chunk of rendered output. Forwards the
+/// chunk to the wrapped writer and tells Go to abort (by returning non-zero)
+/// if the write fails, stashing the error in `ctx` for the caller to surface.
+extern "C" fn write_callback<W: Write>(ctx: *mut c_void, buf: *const u8, len: usize) -> c_int {
+    let ctx = unsafe { &mut *(ctx as *mut WriterCtx<W>) };
+    let slice = unsafe { std::slice::from_raw_parts(buf, len) };
+    match ctx.writer.write_all(slice) {
+        Ok(()) => 0,
+        Err(e) => {
+            ctx.error = Some(e);
+            1
+        }
+    }
+}
+
+/// Renders a Go template directly into `writer`, streaming each chunk out as
+/// the template executes instead of buffering the whole result into a
+/// `String` first. Useful for large outputs (e.g. a `{{range}}` over
+/// thousands of rows) or writing straight to an HTTP response body.
+///
+/// # Arguments
+/// * `template_content` - The Go template string.
+/// * `data` - The data to be used in the template, serialized to JSON as in [`render_template`].
+/// * `escape_html` - Whether to escape HTML characters in the output.
+/// * `writer` - The destination the rendered output is streamed into.
+pub fn render_to_writer<W: Write, T: serde::Serialize>(
+    template_content: &str,
+    data: &T,
+    escape_html: bool,
+    writer: &mut W,
+) -> Result<(), TemplateError> {
+    let c_template_content = CString::new(template_content).map_err(|e| {
+        TemplateError(format!(
+            "Failed to convert template content to CString: {}",
+            e
+        ))
+    })?;
+
+    let json_data_string = serde_json::to_string(data)
+        .map_err(|e| TemplateError(format!("Failed to serialize data to JSON: {}", e)))?;
+    let c_json_data = CString::new(json_data_string).map_err(|e| {
+        TemplateError(format!(
+            "Failed to convert JSON data string to CString: {}",
+            e
+        ))
+    })?;
+
+    let c_funcs_json = registered_funcs_json()?;
+
+    let mut ctx = WriterCtx {
+        writer,
+        error: None,
+    };
+
+    let error_ptr = unsafe {
+        RenderTemplateToWriter(
+            c_template_content.as_ptr() as *mut i8,
+            c_json_data.as_ptr() as *mut i8,
+            escape_html,
+            c_funcs_json.as_ptr() as *mut i8,
+            Some(invoke_func),
+            Some(free_invoke_result),
+            Some(write_callback::<W>),
+            &mut ctx as *mut WriterCtx<W> as *mut c_void,
+        )
+    };
+
+    let error = unsafe { CStr::from_ptr(error_ptr).to_string_lossy().into_owned() };
+    unsafe { FreeResultString(error_ptr) };
+
+    if let Some(io_err) = ctx.error {
+        return Err(TemplateError(format!(
+            "I/O error while writing output: {}",
+            io_err
+        )));
+    }
+    if !error.is_empty() {
+        return Err(TemplateError(error));
+    }
+    Ok(())
+}
+
+/// A Go template that has already been parsed and can be executed
+/// repeatedly without paying the parsing cost again.
+///
+/// The parsed `*template.Template` lives on the Go side, keyed by a handle
+/// stored here. Dropping a `Template` frees that entry.
+#[derive(Debug)]
+pub struct Template {
+    handle: u64,
+}
+
+impl Template {
+    /// Parses `template_content` once, returning a reusable handle.
+    ///
+    /// # Arguments
+    /// * `template_content` - The Go template string.
+    /// * `escape_html` - Whether to parse with `html/template` (escaping) or `text/template`.
+    pub fn new(template_content: &str, escape_html: bool) -> Result<Self, TemplateError> {
+        let c_template_content = CString::new(template_content).map_err(|e| {
+            TemplateError(format!(
+                "Failed to convert template content to CString: {}",
+                e
+            ))
+        })?;
+
+        let c_funcs_json = registered_funcs_json()?;
+
+        let result = unsafe {
+            ParseTemplate(
+                c_template_content.as_ptr() as *mut i8,
+                escape_html,
+                c_funcs_json.as_ptr() as *mut i8,
+                Some(invoke_func),
+                Some(free_invoke_result),
+            )
+        };
+
+        let error = unsafe { CStr::from_ptr(result.error).to_string_lossy().into_owned() };
+        unsafe { FreeResultString(result.error) };
+
+        if !error.is_empty() {
+            return Err(TemplateError(error));
+        }
+
+        Ok(Template {
+            handle: result.handle,
+        })
+    }
+
+    /// Executes this already-parsed template against `data`.
+    ///
+    /// # Arguments
+    /// * `data` - The data to be used in the template, serialized to JSON as in [`render_template`].
+    pub fn render<T: serde::Serialize>(&self, data: &T) -> Result<String, TemplateError> {
+        let json_data_string = serde_json::to_string(data)
+            .map_err(|e| TemplateError(format!("Failed to serialize data to JSON: {}", e)))?;
+        let c_json_data = CString::new(json_data_string).map_err(|e| {
+            TemplateError(format!(
+                "Failed to convert JSON data string to CString: {}",
+                e
+            ))
+        })?;
+
+        let result = unsafe { ExecuteTemplate(self.handle, c_json_data.as_ptr() as *mut i8) };
+
+        let output = unsafe { CStr::from_ptr(result.output).to_string_lossy().into_owned() };
+        let error = unsafe { CStr::from_ptr(result.error).to_string_lossy().into_owned() };
+        unsafe {
+            FreeResultString(result.output);
+            FreeResultString(result.error);
+        }
+
+        if !error.is_empty() {
+            Err(TemplateError(error))
+        } else {
+            Ok(output)
+        }
+    }
+
+    /// Parses the Go template file at `path`, letting Go read and parse it
+    /// (via `template.ParseFiles`) so its behavior matches Go exactly. The
+    /// template is named after the file's base name, as Go does.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the template file.
+    /// * `escape_html` - Whether to parse with `html/template` (escaping) or `text/template`.
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+        escape_html: bool,
+    ) -> Result<Self, TemplateError> {
+        let c_path = CString::new(path.as_ref().to_string_lossy().as_ref()).map_err(|e| {
+            TemplateError(format!("Failed to convert path to CString: {}", e))
+        })?;
+        let c_funcs_json = registered_funcs_json()?;
+
+        let result = unsafe {
+            ParseTemplateFile(
+                c_path.as_ptr() as *mut i8,
+                escape_html,
+                c_funcs_json.as_ptr() as *mut i8,
+                Some(invoke_func),
+                Some(free_invoke_result),
+            )
+        };
+
+        let error = unsafe { CStr::from_ptr(result.error).to_string_lossy().into_owned() };
+        unsafe { FreeResultString(result.error) };
+
+        if !error.is_empty() {
+            return Err(TemplateError(error));
+        }
+
+        Ok(Template {
+            handle: result.handle,
+        })
+    }
+}
+
+impl Drop for Template {
+    fn drop(&mut self) {
+        unsafe { FreeTemplate(self.handle) };
+    }
+}
+
+/// An error encountered while parsing a [`TemplateSet`], naming the
+/// template whose source failed to parse (if the failure could be
+/// attributed to a specific one).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateSetError {
+    /// Name of the template source that failed to parse, or empty if the
+    /// failure happened before any individual template could be attributed
+    /// (e.g. an empty set).
+    pub template_name: String,
+    message: String,
+}
+
+impl fmt::Display for TemplateSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Go Template Error: {}", self.message)
+    }
+}
+
+impl std::error::Error for TemplateSetError {}
+
+#[derive(serde::Serialize)]
+struct NamedSource<'a> {
+    name: &'a str,
+    source: &'a str,
+}
+
+/// A builder for a set of named, associated Go templates, supporting
+/// cross-source `{{define}}`, `{{template}}`, and `{{block}}` references
+/// (e.g. a base layout composed with content partials).
+///
+/// The first source added via [`TemplateSet::add`] becomes the root
+/// template that the others are associated with.
+#[derive(Default)]
+pub struct TemplateSet {
+    sources: Vec<(String, String)>,
+}
+
+impl TemplateSet {
+    /// Creates an empty template set.
+    pub fn new() -> Self {
+        TemplateSet {
+            sources: Vec::new(),
+        }
+    }
+
+    /// Adds a named template source to the set.
+    pub fn add(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.sources.push((name.into(), source.into()));
+        self
+    }
+
+    /// Parses every added source into one group of associated templates.
+    ///
+    /// # Arguments
+    /// * `escape_html` - Whether to parse with `html/template` (escaping) or `text/template`.
+    pub fn build(self, escape_html: bool) -> Result<ParsedTemplateSet, TemplateSetError> {
+        let sources: Vec<NamedSource> = self
+            .sources
+            .iter()
+            .map(|(name, source)| NamedSource { name, source })
+            .collect();
+        let sources_json = serde_json::to_string(&sources).map_err(|e| TemplateSetError {
+            template_name: String::new(),
+            message: format!("Failed to serialize template sources to JSON: {}", e),
+        })?;
+        let c_sources_json = CString::new(sources_json).map_err(|e| TemplateSetError {
+            template_name: String::new(),
+            message: format!("Failed to convert template sources to CString: {}", e),
+        })?;
+
+        let c_funcs_json = registered_funcs_json().map_err(|e| TemplateSetError {
+            template_name: String::new(),
+            message: e.0,
+        })?;
+
+        let result = unsafe {
+            ParseTemplateSet(
+                c_sources_json.as_ptr() as *mut i8,
+                escape_html,
+                c_funcs_json.as_ptr() as *mut i8,
+                Some(invoke_func),
+                Some(free_invoke_result),
+            )
+        };
+
+        let error = unsafe { CStr::from_ptr(result.error).to_string_lossy().into_owned() };
+        let template_name = unsafe {
+            CStr::from_ptr(result.template_name)
+                .to_string_lossy()
+                .into_owned()
+        };
+        unsafe {
+            FreeResultString(result.error);
+            FreeResultString(result.template_name);
+        }
+
+        if !error.is_empty() {
+            return Err(TemplateSetError {
+                template_name,
+                message: error,
+            });
+        }
+
+        Ok(ParsedTemplateSet {
+            handle: result.handle,
+        })
+    }
+
+    /// Loads every template file matching `pattern` (Go glob syntax) into one
+    /// group of associated templates, letting Go's own `ParseGlob` do the
+    /// filesystem walk so glob semantics match Go exactly. Each file is named
+    /// after its base name, so `{{template "header.html" .}}` resolves across
+    /// the set.
+    ///
+    /// # Arguments
+    /// * `pattern` - A glob pattern matching the template files to load.
+    /// * `escape_html` - Whether to parse with `html/template` (escaping) or `text/template`.
+    pub fn from_glob(pattern: &str, escape_html: bool) -> Result<ParsedTemplateSet, TemplateSetError> {
+        let c_pattern = CString::new(pattern).map_err(|e| TemplateSetError {
+            template_name: String::new(),
+            message: format!("Failed to convert glob pattern to CString: {}", e),
+        })?;
+        let c_funcs_json = registered_funcs_json().map_err(|e| TemplateSetError {
+            template_name: String::new(),
+            message: e.0,
+        })?;
+
+        let result = unsafe {
+            ParseTemplateGlob(
+                c_pattern.as_ptr() as *mut i8,
+                escape_html,
+                c_funcs_json.as_ptr() as *mut i8,
+                Some(invoke_func),
+                Some(free_invoke_result),
+            )
+        };
+
+        let error = unsafe { CStr::from_ptr(result.error).to_string_lossy().into_owned() };
+        let template_name = unsafe {
+            CStr::from_ptr(result.template_name)
+                .to_string_lossy()
+                .into_owned()
+        };
+        unsafe {
+            FreeResultString(result.error);
+            FreeResultString(result.template_name);
+        }
+
+        if !error.is_empty() {
+            return Err(TemplateSetError {
+                template_name,
+                message: error,
+            });
+        }
+
+        Ok(ParsedTemplateSet {
+            handle: result.handle,
+        })
+    }
+}
+
+/// A parsed set of associated templates produced by [`TemplateSet::build`].
+#[derive(Debug)]
+pub struct ParsedTemplateSet {
+    handle: u64,
+}
+
+impl ParsedTemplateSet {
+    /// Executes the named entry template (e.g. the root or a `{{define}}`d
+    /// block) against `data`, resolving `{{template}}`/`{{block}}`
+    /// references across the whole set.
+    pub fn render_named<T: serde::Serialize>(
+        &self,
+        entry_name: &str,
+        data: &T,
+    ) -> Result<String, TemplateError> {
+        let json_data_string = serde_json::to_string(data)
+            .map_err(|e| TemplateError(format!("Failed to serialize data to JSON: {}", e)))?;
+        let c_json_data = CString::new(json_data_string).map_err(|e| {
+            TemplateError(format!(
+                "Failed to convert JSON data string to CString: {}",
+                e
+            ))
+        })?;
+        let c_entry_name = CString::new(entry_name).map_err(|e| {
+            TemplateError(format!("Failed to convert entry name to CString: {}", e))
+        })?;
+
+        let result = unsafe {
+            ExecuteTemplateNamed(
+                self.handle,
+                c_entry_name.as_ptr() as *mut i8,
+                c_json_data.as_ptr() as *mut i8,
+            )
+        };
+
+        let output = unsafe { CStr::from_ptr(result.output).to_string_lossy().into_owned() };
+        let error = unsafe { CStr::from_ptr(result.error).to_string_lossy().into_owned() };
+        unsafe {
+            FreeResultString(result.output);
+            FreeResultString(result.error);
+        }
+
+        if !error.is_empty() {
+            Err(TemplateError(error))
+        } else {
+            Ok(output)
+        }
+    }
+}
+
+impl Drop for ParsedTemplateSet {
+    fn drop(&mut self) {
+        unsafe { FreeTemplate(self.handle) };
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +872,201 @@ mod tests {
             "<h2>Shiny Widget</h2><p><p>This is a <strong>great</strong> product!</p></p>"
         );
     }
+
+    // --- Template (解析一次，执行多次) ---
+
+    #[test]
+    fn test_template_parse_once_execute_many() {
+        let template = Template::new("Hello, {{.Name}}!", true).unwrap();
+        assert_eq!(
+            template.render(&json!({"Name": "World"})).unwrap(),
+            "Hello, World!"
+        );
+        assert_eq!(
+            template.render(&json!({"Name": "Rust"})).unwrap(),
+            "Hello, Rust!"
+        );
+    }
+
+    #[test]
+    fn test_template_parse_error() {
+        let err = Template::new("Invalid {{.Template", true).expect_err("should fail to parse");
+        assert!(err.to_string().contains("Failed to parse HTML template"));
+    }
+
+    #[test]
+    fn test_template_no_escape_html() {
+        let template =
+            Template::new("<p>{{.Content}}</p>", false).expect("should parse");
+        let result = template
+            .render(&json!({"Content": "<script>alert('xss')</script>"}))
+            .unwrap();
+        assert_eq!(result, "<p><script>alert('xss')</script></p>");
+    }
+
+    // --- TemplateSet (命名模板集) ---
+
+    #[test]
+    fn test_template_set_layout_and_block() {
+        let set = TemplateSet::new()
+            .add("layout", "<html>{{block \"body\" .}}default{{end}}</html>")
+            .add("body", "{{define \"body\"}}Hello, {{.Name}}!{{end}}")
+            .build(true)
+            .unwrap();
+        assert_eq!(
+            set.render_named("layout", &json!({"Name": "World"})).unwrap(),
+            "<html>Hello, World!</html>"
+        );
+    }
+
+    #[test]
+    fn test_template_set_cross_template_reference() {
+        let set = TemplateSet::new()
+            .add("header", "{{define \"header\"}}== {{.Title}} =={{end}}")
+            .add("page", "{{template \"header\" .}}\nBody")
+            .build(true)
+            .unwrap();
+        assert_eq!(
+            set.render_named("page", &json!({"Title": "Home"})).unwrap(),
+            "== Home ==\nBody"
+        );
+    }
+
+    #[test]
+    fn test_template_set_parse_error_names_template() {
+        let err = TemplateSet::new()
+            .add("good", "Hello")
+            .add("bad", "{{.Unclosed")
+            .build(true)
+            .expect_err("should fail to parse");
+        assert_eq!(err.template_name, "bad");
+    }
+
+    #[test]
+    fn test_template_set_empty_is_error() {
+        let err = TemplateSet::new()
+            .build(true)
+            .expect_err("an empty set should fail to build");
+        assert!(err.to_string().contains("template set must contain"));
+    }
+
+    // --- register_func (自定义模板函数) ---
+
+    #[test]
+    fn test_register_func_called_from_template() {
+        register_func("shout", |args| {
+            let text = args
+                .first()
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "shout expects a string argument".to_string())?;
+            Ok(serde_json::Value::String(text.to_uppercase()))
+        });
+
+        let template = "{{shout .Name}}";
+        let result = render_template(template, &json!({"Name": "world"}), true).unwrap();
+        assert_eq!(result, "WORLD");
+    }
+
+    #[test]
+    fn test_register_func_error_surfaces_as_template_error() {
+        register_func("boom", |_args| Err("always fails".to_string()));
+
+        let err = render_template("{{boom}}", &json!({}), true).expect_err("should error");
+        assert!(err.to_string().contains("always fails"));
+    }
+
+    // --- render_to_writer (流式渲染) ---
+
+    #[test]
+    fn test_render_to_writer_streams_output() {
+        let template = "Items:\n{{range .Items}}- {{.}}\n{{end}}";
+        let data = json!({"Items": ["Apple", "Banana", "Cherry"]});
+        let mut output = Vec::new();
+        render_to_writer(template, &data, true, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Items:\n- Apple\n- Banana\n- Cherry\n"
+        );
+    }
+
+    #[test]
+    fn test_render_to_writer_parse_error() {
+        let mut output = Vec::new();
+        let err = render_to_writer("Invalid {{.Template", &json!({}), true, &mut output)
+            .expect_err("should fail to parse");
+        assert!(err.to_string().contains("Failed to parse HTML template"));
+    }
+
+    // --- render_template_with (自定义分隔符) ---
+
+    #[test]
+    fn test_render_template_with_custom_delims() {
+        let options = RenderOptions {
+            escape_html: true,
+            left_delim: Some("<%".to_string()),
+            right_delim: Some("%>".to_string()),
+        };
+        let result =
+            render_template_with("Hello, <%.Name%>!", &json!({"Name": "World"}), &options)
+                .unwrap();
+        assert_eq!(result, "Hello, World!");
+    }
+
+    #[test]
+    fn test_render_template_with_default_delims_still_work() {
+        let options = RenderOptions {
+            escape_html: true,
+            ..Default::default()
+        };
+        let result =
+            render_template_with("Hello, {{.Name}}!", &json!({"Name": "World"}), &options)
+                .unwrap();
+        assert_eq!(result, "Hello, World!");
+    }
+
+    // --- Template::from_file / TemplateSet::from_glob (文件与 glob 加载) ---
+
+    /// Writes `contents` to `dir/name` and returns the file's path.
+    fn write_temp_template(dir: &std::path::Path, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_template_from_file() {
+        let dir = std::env::temp_dir().join("gotpl_test_from_file");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = write_temp_template(&dir, "greeting.tmpl", "Hello, {{.Name}}!");
+
+        let template = Template::from_file(&path, true).unwrap();
+        let result = template.render(&json!({"Name": "World"})).unwrap();
+        assert_eq!(result, "Hello, World!");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_template_from_file_missing_path_is_error() {
+        let err = Template::from_file("/no/such/file.tmpl", true)
+            .expect_err("missing file should fail to parse");
+        assert!(err.to_string().contains("/no/such/file.tmpl"));
+    }
+
+    #[test]
+    fn test_template_set_from_glob() {
+        let dir = std::env::temp_dir().join("gotpl_test_from_glob");
+        std::fs::create_dir_all(&dir).unwrap();
+        write_temp_template(&dir, "layout.tmpl", "<html>{{template \"body.tmpl\" .}}</html>");
+        write_temp_template(&dir, "body.tmpl", "Hello, {{.Name}}!");
+
+        let pattern = dir.join("*.tmpl").to_string_lossy().into_owned();
+        let set = TemplateSet::from_glob(&pattern, true).unwrap();
+        let result = set
+            .render_named("layout.tmpl", &json!({"Name": "World"}))
+            .unwrap();
+        assert_eq!(result, "<html>Hello, World!</html>");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }